@@ -1,8 +1,12 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
 //! Wrap `Read` with a read statistics logger.
 
 //! ## Usage example
 //!
 //! ```
+//! # #[cfg(feature = "std")]
+//! # {
 //! use std::fs::File;
 //! use std::io::{BufReader, Read};
 //! use read_logger::{Level, ReadLogger};
@@ -18,6 +22,7 @@
 //! // BufReader does only one read() call:
 //! assert_eq!(read_logger.stats().read_count, 1);
 //! assert!(read_logger.stats().bytes_total > 200);
+//! # }
 //! ```
 
 //! Run with (using `env_logger`):
@@ -31,17 +36,53 @@
 //! [2023-09-02T18:41:41Z DEBUG read_logger] Read 0-236 (237 bytes). Total requests: 1 (237 bytes),READ,0,236,237,8192,1,237
 //! ```
 
+//! When the logger is dropped, it logs one more line with the read-size histogram as
+//! `bucket:count` pairs (237 bytes fall into bucket 8, `[128, 256)`):
+//! ```text
+//! [2023-09-02T18:41:41Z DEBUG read_logger] Read histogram (bucket:count),READ,8:1
+//! ```
+
+//! ## `no_std`
+//!
+//! The `std` feature is enabled by default. Disable it (`default-features = false`) to build
+//! against `no_std_io`, a minimal in-crate `Read`/`Write`/`Seek` substitute, for use on
+//! embedded targets. Vectored reads rely on the OS-specific `IoSliceMut`, so
+//! `read_vectored` is only available with `std`.
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+#[cfg(not(feature = "std"))]
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+#[cfg(not(feature = "std"))]
+pub mod no_std_io;
+
 use log::log;
 pub use log::Level;
-use std::io::{Error, Read, Seek, SeekFrom};
+#[cfg(feature = "std")]
+use std::io::{Error, ErrorKind, IoSliceMut, Read, Seek, SeekFrom, Write};
+#[cfg(feature = "std")]
 use std::result::Result;
 
+#[cfg(not(feature = "std"))]
+use core::result::Result;
+#[cfg(not(feature = "std"))]
+use no_std_io::{Error, ErrorKind, Read, Seek, SeekFrom, Write};
+
 /// Log reads, counts and totals
 pub struct ReadStatsLogger {
     tag: String,
     level: Level,
     pub read_count: usize,
     pub bytes_total: usize,
+    /// Power-of-two bucket histogram of read lengths.
+    /// Bucket 0 counts zero-length reads, bucket `i` (i >= 1) counts lengths in `[2^(i-1), 2^i)`.
+    /// Lengths of 2^32 bytes or more all fall into the last bucket.
+    histogram: [usize; 33],
 }
 
 impl ReadStatsLogger {
@@ -55,6 +96,7 @@ impl ReadStatsLogger {
             level,
             read_count: 0,
             bytes_total: 0,
+            histogram: [0; 33],
         }
     }
     /// Log a read request with effective `length` and `request_length` starting at `begin`
@@ -62,6 +104,7 @@ impl ReadStatsLogger {
         // Wraparound is ok
         self.read_count += 1;
         self.bytes_total += length;
+        self.histogram[Self::bucket(length).min(self.histogram.len() - 1)] += 1;
         let end = (begin + length).saturating_sub(1);
         log!(
             self.level,
@@ -73,45 +116,269 @@ impl ReadStatsLogger {
             self.bytes_total,
         );
     }
+    fn bucket(length: usize) -> usize {
+        if length == 0 {
+            0
+        } else {
+            (usize::BITS - length.leading_zeros()) as usize
+        }
+    }
+    /// Power-of-two bucket histogram of read lengths, see [Self::histogram] field docs for bucketing
+    pub fn histogram(&self) -> &[usize; 33] {
+        &self.histogram
+    }
+    /// Emit a summary log line listing the populated histogram buckets as `bucket:count` pairs
+    pub fn summary(&self) {
+        let buckets: Vec<String> = self
+            .histogram
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(bucket, count)| format!("{bucket}:{count}"))
+            .collect();
+        log!(
+            self.level,
+            "Read histogram (bucket:count),{},{}",
+            self.tag,
+            buckets.join(",")
+        );
+    }
+}
+
+impl Drop for ReadStatsLogger {
+    fn drop(&mut self) {
+        self.summary();
+    }
+}
+
+/// No-op trace sink used by [ReadLogger] until [ReadLogger::with_trace] is called
+pub struct NoTrace;
+
+impl Write for NoTrace {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
-/// Wrap `Read` with a [ReadStatsLogger]
-pub struct ReadLogger<T: Read> {
+/// Wrap `Read` with a [ReadStatsLogger], optionally also recording a replayable
+/// access trace to `W` via [ReadLogger::with_trace]
+pub struct ReadLogger<T: Read, W: Write = NoTrace> {
     inner: T,
     logger: ReadStatsLogger,
+    pos: u64,
+    trace: W,
 }
 
-impl<T: Read> ReadLogger<T> {
+impl<T: Read> ReadLogger<T, NoTrace> {
     pub fn new(read: T, level: Level, tag: &str) -> Self {
         ReadLogger {
             inner: read,
             logger: ReadStatsLogger::new(level, tag),
+            pos: 0,
+            trace: NoTrace,
         }
     }
+}
+
+impl<T: Read, W: Write> ReadLogger<T, W> {
     pub fn stats(&self) -> &ReadStatsLogger {
         &self.logger
     }
+    /// Current position in the stream, as tracked from reads and seeks
+    pub fn pos(&self) -> u64 {
+        self.pos
+    }
+    /// Record every read as a length-prefixed `(begin, length)` record to `sink`,
+    /// see [write_trace_record] for the on-disk format
+    pub fn with_trace<W2: Write>(self, sink: W2) -> ReadLogger<T, W2> {
+        ReadLogger {
+            inner: self.inner,
+            logger: self.logger,
+            pos: self.pos,
+            trace: sink,
+        }
+    }
 }
 
-impl<T: Read> Read for ReadLogger<T> {
+impl<T: Read, W: Write> ReadLogger<T, W> {
+    /// Record a trace entry for a read that already succeeded against `inner`.
+    /// A failure here must not turn an already-successful read into an `Err`,
+    /// so it is logged and otherwise ignored rather than propagated.
+    fn trace(&mut self, begin: u64, length: u32) {
+        if let Err(err) = write_trace_record(&mut self.trace, begin, length) {
+            log!(
+                self.logger.level,
+                "Failed to write access trace record ({begin}, {length}): {err}"
+            );
+        }
+    }
+}
+
+impl<T: Read, W: Write> Read for ReadLogger<T, W> {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
         let length = self.inner.read(buf)?;
-        self.logger.log(0, length, buf.len());
+        let begin = self.pos;
+        self.logger.log(begin as usize, length, buf.len());
+        self.pos += length as u64;
+        self.trace(begin, length as u32);
+        Ok(length)
+    }
+
+    #[cfg(feature = "std")]
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> Result<usize, Error> {
+        let length = self.inner.read_vectored(bufs)?;
+        let begin = self.pos;
+        let request_length = bufs.iter().map(|buf| buf.len()).sum();
+        self.logger.log(begin as usize, length, request_length);
+        self.pos += length as u64;
+        self.trace(begin, length as u32);
         Ok(length)
     }
 }
 
-impl<T: Read + Seek> Seek for ReadLogger<T> {
+impl<T: Read + Seek, W: Write> Seek for ReadLogger<T, W> {
     fn seek(&mut self, pos: SeekFrom) -> Result<u64, Error> {
-        self.inner.seek(pos)
+        self.pos = self.inner.seek(pos)?;
+        Ok(self.pos)
     }
 }
 
-#[cfg(test)]
+/// Log writes, flushes, counts and totals
+pub struct WriteStatsLogger {
+    tag: String,
+    level: Level,
+    pub write_count: usize,
+    pub bytes_total: usize,
+    pub flush_count: usize,
+}
+
+impl WriteStatsLogger {
+    pub fn new(level: Level, tag: &str) -> Self {
+        log!(
+            level,
+            "Initialize Write logger `{tag}`,tag,begin,end,length,request_length,count,bytes_total"
+        );
+        WriteStatsLogger {
+            tag: tag.to_string(),
+            level,
+            write_count: 0,
+            bytes_total: 0,
+            flush_count: 0,
+        }
+    }
+    /// Log a write request with effective `length` and `request_length` starting at `begin`
+    pub fn log(&mut self, begin: usize, length: usize, request_length: usize) {
+        // Wraparound is ok
+        self.write_count += 1;
+        self.bytes_total += length;
+        let end = (begin + length).saturating_sub(1);
+        log!(
+            self.level,
+            "Write {begin}-{end} ({length} bytes). Total requests: {} ({} bytes),{},{begin},{end},{length},{request_length},{},{}",
+            self.write_count,
+            self.bytes_total,
+            self.tag,
+            self.write_count,
+            self.bytes_total,
+        );
+    }
+    /// Log a flush, counted separately from writes
+    pub fn log_flush(&mut self) {
+        self.flush_count += 1;
+        log!(
+            self.level,
+            "Flush. Total flushes: {},{},flush,flush,0,0,{},{}",
+            self.flush_count,
+            self.tag,
+            self.write_count,
+            self.bytes_total,
+        );
+    }
+}
+
+/// Wrap `Write` with a [WriteStatsLogger]
+pub struct WriteLogger<T: Write> {
+    inner: T,
+    logger: WriteStatsLogger,
+}
+
+impl<T: Write> WriteLogger<T> {
+    pub fn new(write: T, level: Level, tag: &str) -> Self {
+        WriteLogger {
+            inner: write,
+            logger: WriteStatsLogger::new(level, tag),
+        }
+    }
+    pub fn stats(&self) -> &WriteStatsLogger {
+        &self.logger
+    }
+}
+
+impl<T: Write> Write for WriteLogger<T> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let length = self.inner.write(buf)?;
+        self.logger.log(0, length, buf.len());
+        Ok(length)
+    }
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), Error> {
+        self.inner.write_all(buf)?;
+        self.logger.log(0, buf.len(), buf.len());
+        Ok(())
+    }
+    fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush()?;
+        self.logger.log_flush();
+        Ok(())
+    }
+}
+
+/// Write one length-prefixed access-trace record: an 8-byte big-endian `begin` offset
+/// followed by a 4-byte big-endian `length`, so the stream reads as `oooooooollll` repeated.
+/// Pair with [ReadLogger::with_trace] to capture a trace, and with [TraceReader] to replay it.
+pub fn write_trace_record<W: Write>(sink: &mut W, begin: u64, length: u32) -> Result<(), Error> {
+    sink.write_all(&begin.to_be_bytes())?;
+    sink.write_all(&length.to_be_bytes())?;
+    Ok(())
+}
+
+/// Read back an access trace written by [write_trace_record] / [ReadLogger::with_trace]
+/// as an iterator of `(begin, length)` pairs
+pub struct TraceReader<R: Read> {
+    source: R,
+}
+
+impl<R: Read> TraceReader<R> {
+    pub fn new(source: R) -> Self {
+        TraceReader { source }
+    }
+}
+
+impl<R: Read> Iterator for TraceReader<R> {
+    type Item = Result<(u64, u32), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut begin = [0; 8];
+        match self.source.read_exact(&mut begin) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return None,
+            Err(e) => return Some(Err(e)),
+        }
+        let mut length = [0; 4];
+        if let Err(e) = self.source.read_exact(&mut length) {
+            return Some(Err(e));
+        }
+        Some(Ok((u64::from_be_bytes(begin), u32::from_be_bytes(length))))
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
     use std::fs::File;
-    use std::io::{BufReader, Cursor};
+    use std::io::{BufReader, BufWriter, Cursor};
 
     fn init_logger() {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -127,6 +394,32 @@ mod tests {
         assert_eq!(stats.bytes_total, 8);
     }
 
+    #[test]
+    fn histogram() {
+        init_logger();
+        let mut stats = ReadStatsLogger::new(Level::Info, "READ");
+        stats.log(0, 0, 4);
+        stats.log(0, 1, 4);
+        stats.log(0, 3, 4);
+        stats.log(0, 4, 4);
+        // bucket 0: length 0, bucket 1: [1,2), bucket 2: [2,4), bucket 3: [4,8)
+        assert_eq!(stats.histogram()[0], 1);
+        assert_eq!(stats.histogram()[1], 1);
+        assert_eq!(stats.histogram()[2], 1);
+        assert_eq!(stats.histogram()[3], 1);
+        stats.summary();
+    }
+
+    #[test]
+    fn histogram_clamps_huge_reads() {
+        init_logger();
+        let mut stats = ReadStatsLogger::new(Level::Info, "READ");
+        // On 64-bit targets these lengths would bucket well past index 32
+        stats.log(0, 1 << 32, 1 << 32);
+        stats.log(0, 1 << 40, 1 << 40);
+        assert_eq!(stats.histogram()[32], 2);
+    }
+
     #[test]
     fn read_cursor() {
         init_logger();
@@ -144,6 +437,7 @@ mod tests {
         assert_eq!(n, 2);
         // We count effective bytes, not requested bytes
         assert_eq!(reader.stats().bytes_total, 10);
+        assert_eq!(reader.pos(), 10);
     }
 
     #[test]
@@ -158,6 +452,74 @@ mod tests {
         assert_eq!(&bytes, b"4567");
         assert_eq!(reader.stats().read_count, 1);
         assert_eq!(reader.stats().bytes_total, 4);
+        assert_eq!(reader.pos(), 8);
+    }
+
+    #[test]
+    fn read_vectored() {
+        init_logger();
+        let text = "0123456789";
+        let mut reader = ReadLogger::new(Cursor::new(text), Level::Info, "READ");
+
+        let mut a = [0; 4];
+        let mut b = [0; 4];
+        let n = reader
+            .read_vectored(&mut [IoSliceMut::new(&mut a), IoSliceMut::new(&mut b)])
+            .unwrap();
+        assert_eq!(n, 8);
+        assert_eq!(&a, b"0123");
+        assert_eq!(&b, b"4567");
+        // One log call per syscall, not per slice
+        assert_eq!(reader.stats().read_count, 1);
+        assert_eq!(reader.stats().bytes_total, 8);
+        assert_eq!(reader.pos(), 8);
+    }
+
+    #[test]
+    fn trace_roundtrip() {
+        init_logger();
+        let text = "0123456789";
+        let mut trace = Vec::new();
+        let mut reader =
+            ReadLogger::new(Cursor::new(text), Level::Info, "READ").with_trace(&mut trace);
+
+        let mut bytes = [0; 4];
+        reader.read_exact(&mut bytes).unwrap();
+        reader.seek(SeekFrom::Start(8)).unwrap();
+        reader.read_exact(&mut bytes[..2]).unwrap();
+        drop(reader);
+
+        let records: Vec<_> = TraceReader::new(Cursor::new(trace))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records, vec![(0, 4), (8, 2)]);
+    }
+
+    struct FailingSink;
+
+    impl Write for FailingSink {
+        fn write(&mut self, _buf: &[u8]) -> Result<usize, Error> {
+            Err(Error::other("sink unavailable"))
+        }
+        fn flush(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn trace_write_failure_does_not_fail_the_read() {
+        init_logger();
+        let text = "0123456789";
+        let mut reader =
+            ReadLogger::new(Cursor::new(text), Level::Info, "READ").with_trace(FailingSink);
+
+        let mut bytes = [0; 4];
+        // The underlying read succeeds even though the trace sink always errors
+        let n = reader.read(&mut bytes).unwrap();
+        assert_eq!(n, 4);
+        assert_eq!(&bytes, b"0123");
+        assert_eq!(reader.stats().read_count, 1);
+        assert_eq!(reader.pos(), 4);
     }
 
     #[test]
@@ -190,4 +552,49 @@ mod tests {
         assert_eq!(read_logger.stats().read_count, 1);
         assert!(read_logger.stats().bytes_total > 200);
     }
+
+    #[test]
+    fn check_write_stats() {
+        init_logger();
+        let mut stats = WriteStatsLogger::new(Level::Info, "WRITE");
+        stats.log(0, 4, 4);
+        stats.log(4, 4, 4);
+        assert_eq!(stats.write_count, 2);
+        assert_eq!(stats.bytes_total, 8);
+    }
+
+    #[test]
+    fn write_cursor() {
+        init_logger();
+        let mut writer = WriteLogger::new(Cursor::new(Vec::new()), Level::Info, "WRITE");
+
+        writer.write_all(b"0123").unwrap();
+        writer.write_all(b"4567").unwrap();
+        assert_eq!(writer.stats().write_count, 2);
+        assert_eq!(writer.stats().bytes_total, 8);
+    }
+
+    #[test]
+    fn buf_writer() {
+        init_logger();
+        let mut cursor = WriteLogger::new(Cursor::new(Vec::new()), Level::Debug, "WRITE");
+        // To be able to access stats after writing, we borrow cursor to BufWriter
+        let mut buffer = BufWriter::new(&mut cursor);
+
+        buffer.write_all(b"0123").unwrap();
+        buffer.write_all(b"4567").unwrap();
+        buffer.flush().unwrap();
+        drop(buffer);
+        assert_eq!(cursor.stats().write_count, 1);
+        assert_eq!(cursor.stats().bytes_total, 8);
+    }
+
+    #[test]
+    fn flush() {
+        init_logger();
+        let mut writer = WriteLogger::new(Cursor::new(Vec::new()), Level::Info, "WRITE");
+        writer.flush().unwrap();
+        writer.flush().unwrap();
+        assert_eq!(writer.stats().flush_count, 2);
+    }
 }